@@ -1,12 +1,14 @@
 use std::{
     borrow::Cow,
     env::args,
+    fmt::Write as _,
     fs::File,
     io::{self, BufReader, Read, Stdout, Write},
     mem,
     os::fd::AsRawFd,
-    path::Path,
+    path::{Path, PathBuf},
     slice,
+    time::Instant,
 };
 
 use libc::{c_ushort, STDOUT_FILENO, TIOCGWINSZ};
@@ -28,6 +30,36 @@ const fn ctrl_key(b: u8) -> char {
 
 const TAB_SIZE: usize = 4;
 
+const CLASS_NON_PRINTABLE: u8 = 0;
+const CLASS_ALNUM: u8 = 1;
+const CLASS_PUNCTUATION: u8 = 2;
+const CLASS_SPACE: u8 = 3;
+
+const CHAR_CLASS: [u8; 256] = build_char_class_table();
+
+const fn build_char_class_table() -> [u8; 256] {
+    let mut table = [CLASS_NON_PRINTABLE; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = match i as u8 {
+            b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'_' => CLASS_ALNUM,
+            b' ' | b'\t' | b'\n' | b'\r' => CLASS_SPACE,
+            0x21..=0x7e => CLASS_PUNCTUATION,
+            _ => CLASS_NON_PRINTABLE,
+        };
+        i += 1;
+    }
+    table
+}
+
+fn char_class(c: char) -> u8 {
+    if (c as u32) < 256 {
+        CHAR_CLASS[c as usize]
+    } else {
+        CLASS_ALNUM
+    }
+}
+
 /*** init ***/
 
 fn main() -> io::Result<()> {
@@ -39,6 +71,7 @@ fn main() -> io::Result<()> {
     editor.editor_refresh_screen()?;
     // Set terminal attributes back to the original termios state
     editor.disable_raw_mode()?;
+    editor.leave_alternate_screen()?;
     Ok(())
 }
 
@@ -51,6 +84,7 @@ pub struct winsize {
     ws_ypixel: c_ushort,
 }
 
+#[derive(Clone, Copy)]
 enum EditorKey {
     ArrowLeft,
     ArrowRight,
@@ -61,9 +95,17 @@ enum EditorKey {
     Home,
     End,
     Delete,
+    WordLeft,
+    WordRight,
     Other(char),
 }
 
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum EditorMode {
+    Normal,
+    Insert,
+}
+
 struct Editor {
     orig_termios: Termios,
     stdin_fd: i32,
@@ -75,6 +117,15 @@ struct Editor {
     row_offset: usize,
     col_offset: usize,
     rope: Rope,
+    filename: Option<PathBuf>,
+    dirty: bool,
+    find_last_match: Option<usize>,
+    find_direction: isize,
+    mode: EditorMode,
+    pending_keys: Vec<char>,
+    status_message: String,
+    status_message_time: Option<Instant>,
+    prompt_line: Option<String>,
 }
 
 impl Editor {
@@ -83,28 +134,52 @@ impl Editor {
         let stdout = io::stdout();
         // Read current termios settings
         let termios = tcgetattr(stdin_fd)?;
-        let (screen_rows, screen_cols) = Self::get_window_size()?;
-        Ok(Self {
+
+        let mut editor = Self {
             orig_termios: termios,
             stdin_fd,
             stdout,
-            screen_rows: screen_rows as usize,
-            screen_cols: screen_cols as usize,
+            screen_rows: 0,
+            screen_cols: 0,
             cursor_row: 0,
             cursor_col: 0,
             row_offset: 0,
             col_offset: 0,
             rope: Rope::default(),
-        })
+            filename: None,
+            dirty: false,
+            find_last_match: None,
+            find_direction: 1,
+            mode: EditorMode::Normal,
+            pending_keys: Vec::new(),
+            status_message: String::new(),
+            status_message_time: None,
+            prompt_line: None,
+        };
+
+        // Raw mode has to be active before we query the window size: the
+        // cursor-position fallback below reads a DSR reply that arrives with
+        // no trailing newline, which canonical mode would never hand to a
+        // blocking read.
+        editor.enable_raw_mode()?;
+        let (screen_rows, screen_cols) = Self::get_window_size()?;
+        editor.screen_rows = screen_rows as usize;
+        editor.screen_cols = screen_cols as usize;
+
+        Ok(editor)
     }
 
     fn run(&mut self) -> io::Result<()> {
-        // Use a new copy of the termios instance so we can restore the original state later
-        self.enable_raw_mode()?;
+        // Switch to the alternate screen buffer so the user's existing shell
+        // contents are preserved underneath and restored when we quit.
+        self.stdout.write_all(b"\x1b[?1049h")?;
+        self.stdout.flush()?;
+
         let args: Vec<_> = args().collect();
         if args.len() > 1 {
             self.editor_open(Path::new(&args[1]))?;
         }
+        self.editor_set_status_message("HELP: Ctrl-S = save | Ctrl-F = find | Ctrl-Q = quit".to_string());
 
         loop {
             self.editor_refresh_screen()?;
@@ -121,6 +196,27 @@ impl Editor {
         for i in 0..rows {
             self.editor_update_row(i);
         }
+        self.filename = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    fn editor_save(&mut self) -> io::Result<()> {
+        if self.filename.is_none() {
+            self.filename = self
+                .editor_prompt("Save as: ", |_, _, _| {})?
+                .map(PathBuf::from);
+            if self.filename.is_none() {
+                return Ok(());
+            }
+        }
+
+        let filename = self.filename.clone().expect("just checked above");
+        let mut file = File::create(filename)?;
+        for chunk in self.rope.chunks() {
+            file.write_all(chunk.as_bytes())?;
+        }
+        self.dirty = false;
+        self.editor_set_status_message(format!("{} bytes written to disk", self.rope.len_bytes()));
         Ok(())
     }
 
@@ -140,15 +236,135 @@ impl Editor {
         }
     }
 
+    // Movement is allowed to park the cursor on a placeholder row below the
+    // last line (e.g. Page Down on a short file), which isn't a valid `rope`
+    // line index. Pull it back onto the document before any mutation touches
+    // `rope.line_to_char`.
+    fn clamp_cursor_to_document(&mut self) {
+        let max_row = self.rope.len_lines() - 1;
+        if self.cursor_row > max_row {
+            self.cursor_row = max_row;
+        }
+        let row_len = self.current_row_len();
+        if self.cursor_col > row_len {
+            self.cursor_col = row_len;
+        }
+    }
+
+    fn editor_insert_char(&mut self, c: char) {
+        self.clamp_cursor_to_document();
+        let char_idx = self.rope.line_to_char(self.cursor_row) + self.cursor_col;
+        self.rope.insert_char(char_idx, c);
+        self.cursor_col += 1;
+        self.dirty = true;
+    }
+
+    fn editor_insert_newline(&mut self) {
+        self.clamp_cursor_to_document();
+        let char_idx = self.rope.line_to_char(self.cursor_row) + self.cursor_col;
+        self.rope.insert_char(char_idx, '\n');
+        self.cursor_row += 1;
+        self.cursor_col = 0;
+        self.dirty = true;
+    }
+
+    fn editor_del_char(&mut self) {
+        self.clamp_cursor_to_document();
+        if self.cursor_row == 0 && self.cursor_col == 0 {
+            return;
+        }
+
+        let char_idx = self.rope.line_to_char(self.cursor_row) + self.cursor_col;
+        if self.cursor_col > 0 {
+            self.rope.remove(char_idx - 1..char_idx);
+            self.cursor_col -= 1;
+        } else {
+            let prev_row_len = self.row_len(self.cursor_row - 1);
+            self.rope.remove(char_idx - 1..char_idx);
+            self.cursor_row -= 1;
+            self.cursor_col = prev_row_len;
+        }
+        self.dirty = true;
+    }
+
+    /*** find ***/
+    fn editor_find(&mut self) -> io::Result<()> {
+        let saved_cursor_row = self.cursor_row;
+        let saved_cursor_col = self.cursor_col;
+        let saved_row_offset = self.row_offset;
+        let saved_col_offset = self.col_offset;
+        self.find_last_match = None;
+        self.find_direction = 1;
+
+        let query = self.editor_prompt("Search: ", |editor, query, key| {
+            editor.editor_find_callback(query, key);
+        })?;
+
+        if query.is_none() {
+            self.cursor_row = saved_cursor_row;
+            self.cursor_col = saved_cursor_col;
+            self.row_offset = saved_row_offset;
+            self.col_offset = saved_col_offset;
+        }
+
+        Ok(())
+    }
+
+    fn editor_find_callback(&mut self, query: &str, key: EditorKey) {
+        match key {
+            EditorKey::Other('\r') | EditorKey::Other('\x1b') => {
+                self.find_last_match = None;
+                self.find_direction = 1;
+                return;
+            }
+            EditorKey::ArrowRight | EditorKey::ArrowDown => self.find_direction = 1,
+            EditorKey::ArrowLeft | EditorKey::ArrowUp => self.find_direction = -1,
+            _ => {
+                self.find_last_match = None;
+                self.find_direction = 1;
+            }
+        }
+
+        if query.is_empty() {
+            return;
+        }
+
+        let num_rows = self.rope.len_lines();
+        let mut current = self.find_last_match.unwrap_or(num_rows - 1);
+        for _ in 0..num_rows {
+            current = (current as isize + self.find_direction).rem_euclid(num_rows as isize) as usize;
+            let row_slice: Cow<str> = self.get_row(current).into();
+            let row = row_slice.into_owned();
+            if let Some(byte_col) = row.find(query) {
+                self.find_last_match = Some(current);
+                self.cursor_row = current;
+                self.cursor_col = row[..byte_col].chars().count();
+                self.editor_scroll();
+                break;
+            }
+        }
+    }
+
     /*** output ***/
+    // Total rows available for the screen minus the reserved status bar and
+    // message line at the bottom.
+    fn text_rows(&self) -> usize {
+        self.screen_rows.saturating_sub(2)
+    }
+
+    fn editor_set_status_message(&mut self, message: String) {
+        self.status_message = message;
+        self.status_message_time = Some(Instant::now());
+    }
+
     fn editor_scroll(&mut self) {
         // If cursor is above visible window, scroll up to where cursor is
         if self.cursor_row < self.row_offset {
             self.row_offset = self.cursor_row;
         }
         // If cursor is below visible window, scroll down to where cursor is
-        if self.cursor_row >= self.row_offset + self.screen_rows {
-            self.row_offset = self.cursor_row - self.screen_rows + 1;
+        if self.cursor_row >= self.row_offset + self.text_rows() {
+            self.row_offset = self.cursor_row - self.text_rows() + 1;
         }
 
         if self.cursor_col < self.col_offset {
@@ -159,13 +375,14 @@ impl Editor {
         }
     }
 
-    fn editor_draw_rows(&mut self) -> io::Result<()> {
-        for y in 0..self.screen_rows {
+    fn editor_draw_rows(&self, buf: &mut String) {
+        let text_rows = self.text_rows();
+        for y in 0..text_rows {
             // K - erase in line (clear current line)
-            self.stdout.write_all(b"\x1b[K")?;
+            buf.push_str("\x1b[K");
             let file_row = y + self.row_offset;
             if self.rope.len_chars() == 0 || file_row >= self.rope.len_lines() {
-                if self.rope.len_chars() == 0 && y == self.screen_rows / 3 {
+                if self.rope.len_chars() == 0 && y == text_rows / 3 {
                     let mut message = "Kilo editor -- version 0.0.1";
                     if message.len() > self.screen_cols {
                         message = &message[..self.screen_cols];
@@ -173,12 +390,12 @@ impl Editor {
 
                     let mut padding = (self.screen_cols - message.len()) / 2;
                     if padding > 0 {
-                        self.stdout.write_all(b"~")?;
+                        buf.push('~');
                         padding -= 1;
                     }
-                    write!(&mut self.stdout, "{}{message}", " ".repeat(padding))?;
+                    let _ = write!(buf, "{}{message}", " ".repeat(padding));
                 } else {
-                    self.stdout.write_all(b"~")?;
+                    buf.push('~');
                 }
             } else {
                 let mut line_slice = trim_newline(self.rope.line(file_row));
@@ -190,20 +407,70 @@ impl Editor {
                     line_slice = line_slice.slice(self.col_offset..self.col_offset + col_len);
 
                     let s: Cow<str> = line_slice.into();
-                    self.stdout.write_all(s.as_bytes())?;
+                    buf.push_str(&s);
                 }
             }
 
-            if y < self.screen_rows - 1 {
-                self.stdout.write_all(b"\r\n")?;
-            }
+            buf.push_str("\r\n");
         }
+    }
 
-        Ok(())
+    fn editor_draw_status_bar(&self, buf: &mut String) {
+        buf.push_str("\x1b[7m");
+
+        let filename = self
+            .filename
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("[No Name]");
+        let status = format!(
+            "{filename} - {} lines {}",
+            self.rope.len_lines(),
+            if self.dirty { "(modified)" } else { "" }
+        );
+        let position = format!("{}:{}", self.cursor_row + 1, self.cursor_col + 1);
+
+        let status = truncate_to_cols(&status, self.screen_cols);
+        let status_cols = status.chars().count();
+        buf.push_str(&status);
+
+        let mut remaining = self.screen_cols.saturating_sub(status_cols);
+        while remaining > position.len() {
+            buf.push(' ');
+            remaining -= 1;
+        }
+        if remaining == position.len() {
+            buf.push_str(&position);
+        }
+
+        buf.push_str("\x1b[m");
+        buf.push_str("\r\n");
+    }
+
+    fn editor_draw_message_bar(&self, buf: &mut String) {
+        buf.push_str("\x1b[K");
+        if let Some(prompt_line) = &self.prompt_line {
+            buf.push_str(&truncate_to_cols(prompt_line, self.screen_cols));
+            return;
+        }
+
+        let message_is_fresh = self
+            .status_message_time
+            .is_some_and(|t| t.elapsed().as_secs() < 5);
+        if message_is_fresh {
+            buf.push_str(&truncate_to_cols(&self.status_message, self.screen_cols));
+        }
     }
 
     fn editor_refresh_screen(&mut self) -> io::Result<()> {
         self.editor_scroll();
+
+        // Build the entire frame in a single buffer so it can be handed to
+        // stdout with one write_all and one flush, avoiding the flicker of many
+        // small writes.
+        let mut buf = String::new();
+
         // escape sequence
         // \x1b (27) - escape character (mapped to ESC on keyboard)
         // [ - sequence start
@@ -211,25 +478,33 @@ impl Editor {
         // l - reset mode
         // ?25 - cursor
         // hides the cursor
-        self.stdout.write_all(b"\x1b[?25l")?;
+        buf.push_str("\x1b[?25l");
         // H - cursor position
         // same as \x1b[1;1H - position cursor at row 1 column 1
-        self.stdout.write_all(b"\x1b[H")?;
+        buf.push_str("\x1b[H");
 
-        self.editor_draw_rows()?;
+        self.editor_draw_rows(&mut buf);
+        self.editor_draw_status_bar(&mut buf);
+        self.editor_draw_message_bar(&mut buf);
 
-        // Move cursor to the correct position
-        write!(
-            &mut self.stdout,
-            "\x1b[{};{}H",
-            self.cursor_row - self.row_offset + 1,
-            self.cursor_col - self.col_offset + 1
-        )?;
+        // Move cursor to the correct position: to the end of the prompt input
+        // while a prompt is active, otherwise to the cursor's place in the text.
+        if let Some(prompt_line) = &self.prompt_line {
+            let _ = write!(buf, "\x1b[{};{}H", self.screen_rows, prompt_line.chars().count() + 1);
+        } else {
+            let _ = write!(
+                buf,
+                "\x1b[{};{}H",
+                self.cursor_row - self.row_offset + 1,
+                self.cursor_col - self.col_offset + 1
+            );
+        }
         // h - set mode
         // ?25 - cursor
         // shows the cursor
-        self.stdout.write_all(b"\x1b[?25h")?;
+        buf.push_str("\x1b[?25h");
 
+        self.stdout.write_all(buf.as_bytes())?;
         self.stdout.flush()
     }
 
@@ -248,21 +523,23 @@ impl Editor {
             EditorKey::ArrowRight => {
                 if self.cursor_col < self.current_row_len() {
                     self.cursor_col += 1;
-                } else if self.cursor_row < self.rope.len_lines().max(self.screen_rows) - 1 {
+                } else if self.cursor_row < self.rope.len_lines().max(self.text_rows()) - 1 {
                     self.cursor_row += 1;
                     self.cursor_col = 0;
                 }
             }
             EditorKey::ArrowUp if self.cursor_row > 0 => self.cursor_row -= 1,
             EditorKey::ArrowDown
-                if self.cursor_row < self.rope.len_lines().max(self.screen_rows) - 1 =>
+                if self.cursor_row < self.rope.len_lines().max(self.text_rows()) - 1 =>
             {
                 self.cursor_row += 1
             }
             EditorKey::PageUp => self.cursor_row = 0,
-            EditorKey::PageDown => self.cursor_row = self.screen_rows,
+            EditorKey::PageDown => self.cursor_row = self.text_rows(),
             EditorKey::Home => self.cursor_col = 0,
             EditorKey::End => self.cursor_col = self.screen_cols,
+            EditorKey::WordRight => self.editor_move_word_forward(),
+            EditorKey::WordLeft => self.editor_move_word_backward(),
             _ => {}
         }
 
@@ -272,7 +549,68 @@ impl Editor {
         }
     }
 
-    fn get_row(&self, row: usize) -> RopeSlice {
+    fn char_at(&self, row: usize, col: usize) -> char {
+        self.get_row(row).char(col)
+    }
+
+    fn editor_move_word_forward(&mut self) {
+        loop {
+            let row_len = self.current_row_len();
+            if self.cursor_col >= row_len {
+                if self.cursor_row + 1 < self.rope.len_lines() {
+                    self.cursor_row += 1;
+                    self.cursor_col = 0;
+                    continue;
+                }
+                return;
+            }
+
+            let start_class = char_class(self.char_at(self.cursor_row, self.cursor_col));
+            while self.cursor_col < self.current_row_len()
+                && char_class(self.char_at(self.cursor_row, self.cursor_col)) == start_class
+            {
+                self.cursor_col += 1;
+            }
+            while self.cursor_col < self.current_row_len()
+                && char_class(self.char_at(self.cursor_row, self.cursor_col)) == CLASS_SPACE
+            {
+                self.cursor_col += 1;
+            }
+            return;
+        }
+    }
+
+    fn editor_move_word_backward(&mut self) {
+        loop {
+            if self.cursor_col == 0 {
+                if self.cursor_row > 0 {
+                    self.cursor_row -= 1;
+                    self.cursor_col = self.current_row_len();
+                    continue;
+                }
+                return;
+            }
+
+            while self.cursor_col > 0
+                && char_class(self.char_at(self.cursor_row, self.cursor_col - 1)) == CLASS_SPACE
+            {
+                self.cursor_col -= 1;
+            }
+            if self.cursor_col == 0 {
+                continue;
+            }
+
+            let start_class = char_class(self.char_at(self.cursor_row, self.cursor_col - 1));
+            while self.cursor_col > 0
+                && char_class(self.char_at(self.cursor_row, self.cursor_col - 1)) == start_class
+            {
+                self.cursor_col -= 1;
+            }
+            return;
+        }
+    }
+
+    fn get_row(&self, row: usize) -> RopeSlice<'_> {
         let row = self.rope.line(row);
         trim_newline(row)
     }
@@ -317,6 +655,18 @@ impl Editor {
                                     b'8' => EditorKey::End,
                                     _ => EditorKey::Other(c),
                                 }
+                            } else if buf[2] == b';' {
+                                // modifier sequence, e.g. "\x1b[1;5C" for Ctrl-Right
+                                let mut modifier = [0u8; 2];
+                                if stdin_iter.read(&mut modifier)? < 2 {
+                                    EditorKey::Other(c)
+                                } else {
+                                    match (buf[1], modifier[0], modifier[1]) {
+                                        (b'1', b'5', b'C') => EditorKey::WordRight,
+                                        (b'1', b'5', b'D') => EditorKey::WordLeft,
+                                        _ => EditorKey::Other(c),
+                                    }
+                                }
                             } else {
                                 EditorKey::Other(c)
                             }
@@ -339,22 +689,184 @@ impl Editor {
     }
 
     fn editor_process_keypress(&mut self) -> io::Result<bool> {
-        let c = self.editor_read_key()?;
-        if let EditorKey::Other(c) = c {
+        let key = self.editor_read_key()?;
+        if let EditorKey::Other(c) = key {
             if c == ctrl_key(b'q') {
                 return Ok(false);
             }
         }
-        self.editor_move_cursor(c);
+
+        match self.mode {
+            EditorMode::Normal => self.editor_process_normal_keypress(key)?,
+            EditorMode::Insert => self.editor_process_insert_keypress(key)?,
+        }
         Ok(true)
     }
 
+    fn editor_process_insert_keypress(&mut self, key: EditorKey) -> io::Result<()> {
+        match key {
+            EditorKey::Other(c) if c == ctrl_key(b's') => self.editor_save()?,
+            EditorKey::Other(c) if c == ctrl_key(b'f') => self.editor_find()?,
+            EditorKey::Other('\x1b') => self.mode = EditorMode::Normal,
+            EditorKey::Other('\r') => self.editor_insert_newline(),
+            EditorKey::Other(c) if c as u32 == 127 => self.editor_del_char(),
+            EditorKey::Other(c) if !c.is_ascii_control() => self.editor_insert_char(c),
+            _ => self.editor_move_cursor(key),
+        }
+        Ok(())
+    }
+
+    fn editor_process_normal_keypress(&mut self, key: EditorKey) -> io::Result<()> {
+        let EditorKey::Other(c) = key else {
+            self.pending_keys.clear();
+            self.editor_move_cursor(key);
+            return Ok(());
+        };
+
+        if c == ctrl_key(b's') {
+            self.pending_keys.clear();
+            self.editor_save()?;
+            return Ok(());
+        }
+        if c == ctrl_key(b'f') {
+            self.pending_keys.clear();
+            self.editor_find()?;
+            return Ok(());
+        }
+
+        match c {
+            'h' => self.editor_move_cursor(EditorKey::ArrowLeft),
+            'j' => self.editor_move_cursor(EditorKey::ArrowDown),
+            'k' => self.editor_move_cursor(EditorKey::ArrowUp),
+            'l' => self.editor_move_cursor(EditorKey::ArrowRight),
+            'i' => self.mode = EditorMode::Insert,
+            'a' => {
+                if self.cursor_col < self.current_row_len() {
+                    self.cursor_col += 1;
+                }
+                self.mode = EditorMode::Insert;
+            }
+            'x' => self.editor_delete_char_under_cursor(),
+            '0' => self.editor_move_cursor(EditorKey::Home),
+            '$' => self.editor_move_cursor(EditorKey::End),
+            'd' => {
+                if self.pending_keys.last() == Some(&'d') {
+                    self.pending_keys.clear();
+                    self.editor_delete_line();
+                } else {
+                    self.pending_keys.clear();
+                    self.pending_keys.push('d');
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.pending_keys.clear();
+        Ok(())
+    }
+
+    fn editor_delete_char_under_cursor(&mut self) {
+        if self.cursor_col >= self.current_row_len() {
+            return;
+        }
+        let char_idx = self.rope.line_to_char(self.cursor_row) + self.cursor_col;
+        self.rope.remove(char_idx..char_idx + 1);
+        self.dirty = true;
+    }
+
+    fn editor_delete_line(&mut self) {
+        self.clamp_cursor_to_document();
+        let start = self.rope.line_to_char(self.cursor_row);
+        let end = self.rope.line_to_char(self.cursor_row + 1);
+        if start == end {
+            return;
+        }
+        self.rope.remove(start..end);
+        if self.cursor_row >= self.rope.len_lines() && self.cursor_row > 0 {
+            self.cursor_row -= 1;
+        }
+        self.cursor_col = 0;
+        self.dirty = true;
+    }
+
+    // Displays `prompt` on the message line and reads a line of input, echoing
+    // it back as the user types. Backspace edits the buffer, Enter returns the
+    // final string, and Escape cancels the prompt entirely. `callback` is
+    // invoked after every keystroke with the input so far and the key that
+    // was just pressed, so callers like `editor_find` can react live.
+    fn editor_prompt(
+        &mut self,
+        prompt: &str,
+        mut callback: impl FnMut(&mut Self, &str, EditorKey),
+    ) -> io::Result<Option<String>> {
+        let mut input = String::new();
+        let result = loop {
+            self.prompt_line = Some(format!("{prompt}{input}"));
+            self.editor_refresh_screen()?;
+
+            let key = self.editor_read_key()?;
+            match key {
+                EditorKey::Other('\x1b') => {
+                    callback(self, &input, key);
+                    break Ok(None);
+                }
+                EditorKey::Other('\r') if !input.is_empty() => {
+                    callback(self, &input, key);
+                    break Ok(Some(input));
+                }
+                EditorKey::Other(c) if c as u32 == 127 => {
+                    input.pop();
+                }
+                EditorKey::Other(c) if !c.is_ascii_control() => input.push(c),
+                _ => {}
+            }
+            callback(self, &input, key);
+        };
+        self.prompt_line = None;
+        result
+    }
+
     /*** terminal ***/
     fn get_window_size() -> io::Result<(u16, u16)> {
         unsafe {
             let mut size: winsize = mem::zeroed();
-            read_winsize(STDOUT_FILENO, &mut size)?;
-            Ok((size.ws_row, size.ws_col))
+            if read_winsize(STDOUT_FILENO, &mut size).is_ok() {
+                return Ok((size.ws_row, size.ws_col));
+            }
+        }
+        // ioctl isn't reliable over every pty (e.g. some ssh setups), so fall back to
+        // moving the cursor to the bottom-right corner and asking the terminal where
+        // it landed via a Device Status Report query.
+        Self::get_cursor_position()
+    }
+
+    fn get_cursor_position() -> io::Result<(u16, u16)> {
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x1b[999C\x1b[999B\x1b[6n")?;
+        stdout.flush()?;
+
+        let mut stdin = io::stdin();
+        let mut reply = Vec::new();
+        let mut b = 0u8;
+        while stdin.read(slice::from_mut(&mut b))? == 1 {
+            if b == b'R' {
+                break;
+            }
+            reply.push(b);
+        }
+
+        let reply = String::from_utf8_lossy(&reply);
+        let default = (24, 80);
+        let Some(coords) = reply.strip_prefix("\x1b[") else {
+            return Ok(default);
+        };
+        let Some((rows, cols)) = coords.split_once(';') else {
+            return Ok(default);
+        };
+
+        match (rows.parse(), cols.parse()) {
+            (Ok(rows), Ok(cols)) => Ok((rows, cols)),
+            _ => Ok(default),
         }
     }
 
@@ -406,6 +918,22 @@ impl Editor {
 
         tcsetattr(self.stdin_fd, SetArg::TCSAFLUSH, &self.orig_termios)
     }
+
+    fn leave_alternate_screen(&mut self) -> io::Result<()> {
+        self.stdout.write_all(b"\x1b[?1049l")?;
+        self.stdout.flush()
+    }
+}
+
+// Cuts `s` down to at most `max_cols` characters. Unlike a raw
+// `String::truncate(max_cols)`, which counts bytes and panics when that index
+// falls inside a multi-byte UTF-8 sequence, this counts characters.
+fn truncate_to_cols(s: &str, max_cols: usize) -> Cow<'_, str> {
+    if s.chars().count() <= max_cols {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(s.chars().take(max_cols).collect())
+    }
 }
 
 fn trim_newline(row: RopeSlice) -> RopeSlice {